@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use rust_decimal::Decimal;
+
+/// Currency -> rate-to-base table, used to normalize amounts denominated in different currencies
+/// onto a single base currency for totals and diversification checks.
+pub struct ExchangeRates {
+    base_currency: String,
+    rates: HashMap<String, Decimal>,
+}
+
+impl ExchangeRates {
+    pub fn new(base_currency: String, rates: HashMap<String, Decimal>) -> Self {
+        Self {
+            base_currency,
+            rates,
+        }
+    }
+
+    pub fn base_currency(&self) -> &str {
+        &self.base_currency
+    }
+
+    fn rate_to_base(&self, currency: &str) -> Result<Decimal, Box<dyn Error>> {
+        if currency == self.base_currency {
+            Ok(Decimal::ONE)
+        } else {
+            self.rates
+                .get(currency)
+                .copied()
+                .ok_or_else(|| format!("Missing exchange rate for currency {currency}").into())
+        }
+    }
+
+    pub fn to_base(&self, currency: &str, amount: Decimal) -> Result<Decimal, Box<dyn Error>> {
+        Ok(amount
+            .checked_mul(self.rate_to_base(currency)?)
+            .expect("currency conversion overflow"))
+    }
+
+    pub fn convert(
+        &self,
+        from_currency: &str,
+        to_currency: &str,
+        amount: Decimal,
+    ) -> Result<Decimal, Box<dyn Error>> {
+        if from_currency == to_currency {
+            return Ok(amount);
+        }
+        Ok(self
+            .to_base(from_currency, amount)?
+            .checked_div(self.rate_to_base(to_currency)?)
+            .expect("currency conversion overflow"))
+    }
+}
+
+/// Accumulates amounts per currency and reports both the per-currency breakdown and the
+/// normalized base-currency total.
+#[derive(Default)]
+pub struct MultiCurrencyCashAccount {
+    totals: HashMap<String, Decimal>,
+}
+
+impl MultiCurrencyCashAccount {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, currency: &str, amount: Decimal) {
+        let entry = self
+            .totals
+            .entry(currency.to_string())
+            .or_insert(Decimal::ZERO);
+        *entry = entry.checked_add(amount).expect("currency total overflow");
+    }
+
+    pub fn breakdown(&self) -> &HashMap<String, Decimal> {
+        &self.totals
+    }
+
+    pub fn total_in_base(&self, rates: &ExchangeRates) -> Result<Decimal, Box<dyn Error>> {
+        self.totals
+            .iter()
+            .try_fold(Decimal::ZERO, |acc, (currency, &amount)| {
+                Ok(acc
+                    .checked_add(rates.to_base(currency, amount)?)
+                    .expect("base total overflow"))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rates() -> ExchangeRates {
+        let mut rates = HashMap::new();
+        rates.insert("EUR".to_string(), Decimal::new(11, 1)); // 1 EUR = 1.1 USD
+        ExchangeRates::new("USD".to_string(), rates)
+    }
+
+    #[test]
+    fn test_to_base_same_currency_is_a_no_op() {
+        let rates = rates();
+
+        let result = rates.to_base("USD", Decimal::from(100)).unwrap();
+
+        assert_eq!(result, Decimal::from(100));
+    }
+
+    #[test]
+    fn test_convert_cross_currency() {
+        let rates = rates();
+
+        let result = rates.convert("EUR", "USD", Decimal::from(100)).unwrap();
+
+        assert_eq!(result, Decimal::new(110, 0));
+    }
+
+    #[test]
+    fn test_to_base_missing_rate_is_an_error() {
+        let rates = rates();
+
+        let result = rates.to_base("GBP", Decimal::from(100));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_total_in_base_sums_across_currencies() {
+        let rates = rates();
+        let mut account = MultiCurrencyCashAccount::new();
+        account.add("USD", Decimal::from(100));
+        account.add("EUR", Decimal::from(100));
+
+        let total = account.total_in_base(&rates).unwrap();
+
+        assert_eq!(total, Decimal::from(100) + Decimal::new(110, 0));
+    }
+
+    #[test]
+    fn test_total_in_base_missing_rate_is_an_error() {
+        let rates = rates();
+        let mut account = MultiCurrencyCashAccount::new();
+        account.add("GBP", Decimal::from(100));
+
+        let result = account.total_in_base(&rates);
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,369 @@
+use std::error::Error;
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use calamine::{open_workbook, DataType, Ods, RangeDeserializerBuilder, Reader};
+use chrono::NaiveDateTime;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize};
+
+/// A source of named data sections (e.g. "deposits", "banks"), abstracting over the on-disk
+/// format so `run_app` doesn't need to know whether the data lives in a spreadsheet, a TOML
+/// config, or a directory of CSV files.
+pub trait DataSource {
+    fn load<T: DeserializeOwned>(&mut self, section: &str) -> Result<Vec<T>, Box<dyn Error>>;
+
+    fn has_section(&self, section: &str) -> bool;
+
+    /// Like `load`, but returns an empty result instead of an error when the section is absent,
+    /// for data that is optional (e.g. transactions or exchange rates).
+    fn load_optional<T: DeserializeOwned>(
+        &mut self,
+        section: &str,
+    ) -> Result<Vec<T>, Box<dyn Error>> {
+        if !self.has_section(section) {
+            return Ok(Vec::new());
+        }
+        self.load(section)
+    }
+}
+
+/// Opens the data source implied by `path`: a directory is read as one CSV file per section,
+/// a `.toml` file as a TOML config, and anything else as an ODS spreadsheet.
+pub fn open(path: &str) -> Result<AnyDataSource, Box<dyn Error>> {
+    let path_ref = Path::new(path);
+    if path_ref.is_dir() {
+        return Ok(AnyDataSource::Csv(CsvDataSource::open(path)));
+    }
+    match path_ref.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => Ok(AnyDataSource::Toml(TomlDataSource::open(path)?)),
+        _ => Ok(AnyDataSource::Ods(OdsDataSource::open(path)?)),
+    }
+}
+
+/// Dispatches to whichever concrete backend `open` selected for this run.
+pub enum AnyDataSource {
+    Ods(OdsDataSource),
+    Toml(TomlDataSource),
+    Csv(CsvDataSource),
+}
+
+impl DataSource for AnyDataSource {
+    fn load<T: DeserializeOwned>(&mut self, section: &str) -> Result<Vec<T>, Box<dyn Error>> {
+        match self {
+            AnyDataSource::Ods(source) => source.load(section),
+            AnyDataSource::Toml(source) => source.load(section),
+            AnyDataSource::Csv(source) => source.load(section),
+        }
+    }
+
+    fn has_section(&self, section: &str) -> bool {
+        match self {
+            AnyDataSource::Ods(source) => source.has_section(section),
+            AnyDataSource::Toml(source) => source.has_section(section),
+            AnyDataSource::Csv(source) => source.has_section(section),
+        }
+    }
+}
+
+// ---- ODS backend: one sheet per section, in a single spreadsheet file ----
+
+pub struct OdsDataSource {
+    doc: Ods<BufReader<fs::File>>,
+}
+
+impl OdsDataSource {
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            doc: open_workbook(path)?,
+        })
+    }
+
+    /// The pre-existing sheets use capitalized, sometimes multi-word names; sections are the
+    /// lowercase, underscore-separated vocabulary shared with the TOML/CSV backends.
+    fn sheet_name(section: &str) -> &str {
+        match section {
+            "deposits" => "Deposits",
+            "banks" => "Banks",
+            "transactions" => "Transactions",
+            "exchange_rates" => "Exchange Rates",
+            other => other,
+        }
+    }
+}
+
+impl DataSource for OdsDataSource {
+    fn load<T: DeserializeOwned>(&mut self, section: &str) -> Result<Vec<T>, Box<dyn Error>> {
+        let sheet_name = Self::sheet_name(section);
+        let sheet = self
+            .doc
+            .worksheet_range(sheet_name)
+            .ok_or(format!("Can not open sheet {sheet_name}"))?
+            .map_err(|err| format!("Failed to parse sheet: {:?}", err))?;
+
+        let mut ret: Vec<T> = Vec::new();
+        let mut iter = RangeDeserializerBuilder::new().from_range::<_, T>(&sheet)?;
+        while let Some(Ok(row)) = iter.next() {
+            ret.push(row);
+        }
+        Ok(ret)
+    }
+
+    fn has_section(&self, section: &str) -> bool {
+        let sheet_name = Self::sheet_name(section);
+        self.doc.sheet_names().iter().any(|name| name == sheet_name)
+    }
+}
+
+// ---- TOML backend: `[[deposits]]`/`[[banks]]` array-of-tables sections in a single file ----
+
+pub struct TomlDataSource {
+    document: toml::Value,
+}
+
+impl TomlDataSource {
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        Ok(Self {
+            document: text.parse::<toml::Value>()?,
+        })
+    }
+}
+
+impl DataSource for TomlDataSource {
+    fn load<T: DeserializeOwned>(&mut self, section: &str) -> Result<Vec<T>, Box<dyn Error>> {
+        let entries = self
+            .document
+            .get(section)
+            .and_then(|value| value.as_array())
+            .ok_or(format!("Missing [[{section}]] section"))?;
+        entries
+            .iter()
+            .map(|entry| {
+                T::deserialize(entry.clone())
+                    .map_err(|err| format!("Failed to parse [[{section}]] entry: {err}").into())
+            })
+            .collect()
+    }
+
+    fn has_section(&self, section: &str) -> bool {
+        self.document.get(section).is_some()
+    }
+}
+
+// ---- CSV backend: one `<section>.csv` file per section, inside a directory ----
+
+pub struct CsvDataSource {
+    dir: PathBuf,
+}
+
+impl CsvDataSource {
+    pub fn open(path: &str) -> Self {
+        Self {
+            dir: PathBuf::from(path),
+        }
+    }
+
+    fn section_path(&self, section: &str) -> PathBuf {
+        self.dir.join(format!("{section}.csv"))
+    }
+}
+
+impl DataSource for CsvDataSource {
+    fn load<T: DeserializeOwned>(&mut self, section: &str) -> Result<Vec<T>, Box<dyn Error>> {
+        let mut reader = csv::Reader::from_path(self.section_path(section))?;
+        let mut ret = Vec::new();
+        for row in reader.deserialize() {
+            ret.push(row?);
+        }
+        Ok(ret)
+    }
+
+    fn has_section(&self, section: &str) -> bool {
+        self.section_path(section).is_file()
+    }
+}
+
+/// Parses a date cell shared by all three backends: a plain `YYYY-MM-DD` or full
+/// `YYYY-MM-DDTHH:MM:SS` string. Spreadsheet cells, TOML strings and CSV fields all deserialize
+/// into `calamine::DataType`'s `String` variant the same way, so one function covers all of them.
+pub fn deserialize_date<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let data_type = DataType::deserialize(deserializer)?;
+    match &data_type {
+        DataType::String(val) => {
+            if !val.contains('T') {
+                NaiveDateTime::from_str(&(val.clone() + "T00:00:00")).map_err(serde::de::Error::custom)
+            } else {
+                NaiveDateTime::from_str(val).map_err(serde::de::Error::custom)
+            }
+        }
+        _ => panic!("Invalid DataType for DateTime: {:?}", data_type),
+    }
+}
+
+/// Parses a decimal amount cell, whether it arrived as a spreadsheet float/int or as plain text.
+pub fn parse_decimal<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let data_type = DataType::deserialize(deserializer)?;
+    match &data_type {
+        DataType::Float(val) => Ok(Decimal::from_f64(*val).expect("Invalid decimal value")),
+        DataType::Int(val) => Ok(Decimal::from(*val)),
+        DataType::String(val) => Decimal::from_str(val).map_err(serde::de::Error::custom),
+        _ => panic!("Invalid DataType for Decimal: {:?}", data_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct TestRow {
+        name: String,
+        #[serde(deserialize_with = "deserialize_date")]
+        date: NaiveDateTime,
+        #[serde(deserialize_with = "parse_decimal")]
+        amount: Decimal,
+    }
+
+    fn unique_temp_path(label: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "bank-deposit-manager-test-{label}-{}",
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn test_toml_load_parses_bare_and_quoted_values() {
+        let path = unique_temp_path("toml-load").with_extension("toml");
+        fs::write(
+            &path,
+            r#"
+            [[deposits]]
+            name = "Deposit A"
+            date = "2023-01-15"
+            amount = 1000.5
+
+            [[deposits]]
+            name = "Deposit B"
+            date = "2023-02-01T10:30:00"
+            amount = "2500"
+            "#,
+        )
+        .unwrap();
+
+        let mut source = TomlDataSource::open(path.to_str().unwrap()).unwrap();
+        assert!(source.has_section("deposits"));
+        assert!(!source.has_section("banks"));
+
+        let rows: Vec<TestRow> = source.load("deposits").unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                TestRow {
+                    name: "Deposit A".to_string(),
+                    date: NaiveDate::from_ymd_opt(2023, 1, 15)
+                        .unwrap()
+                        .and_hms_opt(0, 0, 0)
+                        .unwrap(),
+                    amount: Decimal::from_str("1000.5").unwrap(),
+                },
+                TestRow {
+                    name: "Deposit B".to_string(),
+                    date: NaiveDate::from_ymd_opt(2023, 2, 1)
+                        .unwrap()
+                        .and_hms_opt(10, 30, 0)
+                        .unwrap(),
+                    amount: Decimal::from_str("2500").unwrap(),
+                },
+            ]
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_toml_load_missing_section_is_error() {
+        let path = unique_temp_path("toml-missing").with_extension("toml");
+        fs::write(&path, "[[deposits]]\nname = \"Only one\"\n").unwrap();
+
+        let mut source = TomlDataSource::open(path.to_str().unwrap()).unwrap();
+        assert!(source.load::<TestRow>("banks").is_err());
+        assert_eq!(source.load_optional::<TestRow>("banks").unwrap(), vec![]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_csv_load_parses_string_cells() {
+        let dir = unique_temp_path("csv-load");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("deposits.csv"),
+            "name,date,amount\nDeposit A,2023-01-15,1000.5\nDeposit B,2023-02-01T10:30:00,2500\n",
+        )
+        .unwrap();
+
+        let mut source = CsvDataSource::open(dir.to_str().unwrap());
+        assert!(source.has_section("deposits"));
+        assert!(!source.has_section("banks"));
+
+        let rows: Vec<TestRow> = source.load("deposits").unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                TestRow {
+                    name: "Deposit A".to_string(),
+                    date: NaiveDate::from_ymd_opt(2023, 1, 15)
+                        .unwrap()
+                        .and_hms_opt(0, 0, 0)
+                        .unwrap(),
+                    amount: Decimal::from_str("1000.5").unwrap(),
+                },
+                TestRow {
+                    name: "Deposit B".to_string(),
+                    date: NaiveDate::from_ymd_opt(2023, 2, 1)
+                        .unwrap()
+                        .and_hms_opt(10, 30, 0)
+                        .unwrap(),
+                    amount: Decimal::from_str("2500").unwrap(),
+                },
+            ]
+        );
+        assert_eq!(source.load_optional::<TestRow>("banks").unwrap(), vec![]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_dispatches_by_path() {
+        let toml_path = unique_temp_path("open-toml").with_extension("toml");
+        fs::write(&toml_path, "[[deposits]]\nname = \"A\"\n").unwrap();
+        assert!(matches!(
+            open(toml_path.to_str().unwrap()).unwrap(),
+            AnyDataSource::Toml(_)
+        ));
+        fs::remove_file(&toml_path).unwrap();
+
+        let csv_dir = unique_temp_path("open-csv");
+        fs::create_dir_all(&csv_dir).unwrap();
+        assert!(matches!(
+            open(csv_dir.to_str().unwrap()).unwrap(),
+            AnyDataSource::Csv(_)
+        ));
+        fs::remove_dir_all(&csv_dir).unwrap();
+
+        // any other extension falls back to the ODS backend; a missing/invalid file fails to open
+        let ods_path = unique_temp_path("open-ods").with_extension("ods");
+        assert!(open(ods_path.to_str().unwrap()).is_err());
+    }
+}
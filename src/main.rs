@@ -1,23 +1,28 @@
+mod currency;
+mod data_source;
+mod deposit_emulator;
+mod tax;
 mod utils;
 use notify_rust::{Hint, Notification};
 use std::{
     collections::HashMap,
     error::Error,
-    str::FromStr,
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use calamine::{open_workbook, DataType, Ods, RangeDeserializerBuilder, Reader};
-use chrono::{Duration, Months, NaiveDateTime};
+use chrono::{Duration, NaiveDateTime};
 use clap::Parser;
 use colored::Colorize;
-use serde::{de::DeserializeOwned, Deserialize};
-use std::io::{Read, Seek};
+use currency::{ExchangeRates, MultiCurrencyCashAccount};
+use data_source::DataSource;
+use deposit_emulator::{DepositEmulator, EmulationResult, Transaction};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tax::TaxProfile;
 
 const GRAPH_TOTAL_DAYS: f32 = 365.0;
 const GRAPH_CELL_DAYS: f32 = 3.0;
-const MIN_BENEFIT: f32 = 10.0;
-const UP_TO_DATE_SECONDS: i64 = 14 * 24 * 60 * 60;
+const MIN_BENEFIT: Decimal = Decimal::from_parts(1000, 0, 0, false, 2);
 
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
@@ -30,6 +35,22 @@ struct Args {
     /// Minimal output with Desktop notifications
     #[arg(short, long, default_value_t = false)]
     notifications: bool,
+
+    /// Currency that totals and diversification checks are normalized to
+    #[arg(long, default_value = "USD")]
+    base_currency: String,
+
+    /// ISO country code selecting the interest-income tax rate and annual tax-free allowance
+    #[arg(long)]
+    country: Option<String>,
+
+    /// Warn this many days before a deposit matures, not just after it already has
+    #[arg(long, default_value_t = 7)]
+    notify_before_days: i64,
+
+    /// Consider the data file stale after this many days without an update
+    #[arg(long, default_value_t = 14)]
+    stale_after_days: i64,
 }
 
 #[derive(Deserialize, Debug, PartialEq, Clone, Copy)]
@@ -48,23 +69,47 @@ enum PayStrategy {
 struct Deposit {
     bank: String,
     name: String,
-    #[serde(deserialize_with = "parse_date_time")]
+    currency: String,
+    #[serde(deserialize_with = "data_source::deserialize_date")]
     date_open: NaiveDateTime,
-    #[serde(deserialize_with = "parse_date_time")]
+    #[serde(deserialize_with = "data_source::deserialize_date")]
     date_close: NaiveDateTime,
-    amount: f32,
-    percent: f32,
+    #[serde(deserialize_with = "data_source::parse_decimal")]
+    amount: Decimal,
+    #[serde(deserialize_with = "data_source::parse_decimal")]
+    percent: Decimal,
     status: DepositStatus,
     pay_strategy: PayStrategy,
 }
 
+#[derive(Deserialize, Debug)]
+struct TransactionRow {
+    name: String,
+    #[serde(deserialize_with = "data_source::deserialize_date")]
+    date: NaiveDateTime,
+    #[serde(deserialize_with = "data_source::parse_decimal")]
+    amount: Decimal,
+}
+
+#[derive(Deserialize, Debug)]
+struct ExchangeRateRow {
+    currency: String,
+    #[serde(deserialize_with = "data_source::parse_decimal")]
+    rate: Decimal,
+}
+
 #[derive(Deserialize, Debug)]
 struct Bank {
     name: String,
-    percent: f32,
-    min_capacity: f32,
-    max_capacity: f32,
-    transfer_comission: f32,
+    currency: String,
+    #[serde(deserialize_with = "data_source::parse_decimal")]
+    percent: Decimal,
+    #[serde(deserialize_with = "data_source::parse_decimal")]
+    min_capacity: Decimal,
+    #[serde(deserialize_with = "data_source::parse_decimal")]
+    max_capacity: Decimal,
+    #[serde(deserialize_with = "data_source::parse_decimal")]
+    transfer_comission: Decimal,
     pay_strategy: PayStrategy,
 }
 
@@ -86,27 +131,77 @@ fn run_app(args: &Args) -> Result<(), Box<dyn Error>> {
     let path = &args.data;
     // let path = std::fs::read_to_string(".path")?;
     // println!("File: {}", path.bold());
-    let mut doc: Ods<_> = open_workbook(path)?;
+    let mut source = data_source::open(path)?;
 
-    let deposits_own: Vec<Deposit> = read_sheet(&mut doc, "Deposits")?;
+    let deposits_own: Vec<Deposit> = source.load("deposits")?;
     let deposits: Vec<&Deposit> = deposits_own
         .iter()
         .filter(|&dep| dep.status == DepositStatus::Active)
         .collect();
-    let banks_own: Vec<Bank> = read_sheet(&mut doc, "Banks")?;
+    let banks_own: Vec<Bank> = source.load("banks")?;
     let banks: Vec<&Bank> = banks_own.iter().collect();
 
+    let transaction_rows: Vec<TransactionRow> = source.load_optional("transactions")?;
+    let mut transactions_by_deposit: HashMap<String, Vec<Transaction>> = HashMap::new();
+    for row in transaction_rows {
+        transactions_by_deposit
+            .entry(row.name)
+            .or_insert_with(Vec::new)
+            .push(Transaction {
+                date: row.date,
+                amount: row.amount,
+            });
+    }
+
+    let rate_rows: Vec<ExchangeRateRow> = source.load_optional("exchange_rates")?;
+    let rates = rate_rows
+        .into_iter()
+        .map(|row| (row.currency, row.rate))
+        .collect();
+    let exchange_rates = ExchangeRates::new(args.base_currency.clone(), rates);
+    let tax_profile = TaxProfile::for_country(args.country.as_deref());
+
     if args.notifications {
-        notify_exists_expired(&args.data)?;
-        notify_outdated_data(&deposits)?;
+        let mut messages: Vec<String> = vec![];
+        if let Some(message) = check_stale_data(&args.data, args.stale_after_days)? {
+            messages.push(message);
+        }
+        messages.extend(upcoming_maturities(&deposits, args.notify_before_days));
+        if let Some((_, best_suggestion)) = build_suggestions(
+            &deposits,
+            &banks,
+            &transactions_by_deposit,
+            &exchange_rates,
+            &tax_profile,
+        )?
+        .into_iter()
+        .max_by_key(|(benefit, _)| *benefit)
+        {
+            messages.push(format!("Best reopen suggestion: {best_suggestion}"));
+        }
+        if !messages.is_empty() {
+            notify(&messages.join("\n"));
+        }
     } else {
         println!();
         println!("{}", "   Graphics   ".bold().black().on_yellow());
-        print_deposit_graph(&deposits);
+        print_deposit_graph(
+            &deposits,
+            &banks,
+            &transactions_by_deposit,
+            &exchange_rates,
+            &tax_profile,
+        )?;
 
         println!();
         println!("{}", "   Suggestions   ".bold().black().on_yellow());
-        print_suggestions(&deposits, &banks);
+        print_suggestions(
+            &deposits,
+            &banks,
+            &transactions_by_deposit,
+            &exchange_rates,
+            &tax_profile,
+        )?;
     }
 
     Ok(())
@@ -122,30 +217,45 @@ fn notify(message: &str) {
         .unwrap();
 }
 
-fn notify_exists_expired(path: &String) -> Result<(), Box<dyn Error>> {
+/// Returns a warning message when the data file hasn't been updated in `stale_after_days` days.
+fn check_stale_data(path: &String, stale_after_days: i64) -> Result<Option<String>, Box<dyn Error>> {
     let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
     let modified = std::fs::metadata(path)?
         .modified()?
         .duration_since(UNIX_EPOCH)?
         .as_secs() as i64;
     let age = Duration::seconds(now - modified);
-    if age.num_seconds() > UP_TO_DATE_SECONDS {
-        notify(format!("Data outdated. Last update {} days ago", age.num_days()).as_str());
+    if age.num_seconds() > stale_after_days * 24 * 60 * 60 {
+        Ok(Some(format!(
+            "Data outdated. Last update {} days ago",
+            age.num_days()
+        )))
+    } else {
+        Ok(None)
     }
-    Ok(())
 }
 
-fn notify_outdated_data(deposits: &Vec<&Deposit>) -> Result<(), Box<dyn Error>> {
+/// Lists deposits that have already expired or will mature within `notify_before_days` days.
+fn upcoming_maturities(deposits: &Vec<&Deposit>, notify_before_days: i64) -> Vec<String> {
     let now = chrono::offset::Local::now().naive_local();
+    let mut messages = vec![];
     for dep in deposits {
         let duration = dep.date_close - dep.date_open;
         let opened_ago = now - dep.date_open;
         let close_days = (duration - opened_ago).num_days();
-        if close_days < 0 {
-            notify("Expired deposits have been found");
+        if close_days < notify_before_days {
+            let status = if close_days < 0 {
+                "has expired"
+            } else {
+                "matures soon"
+            };
+            messages.push(format!(
+                "Deposit '{}' at {} {status} (in {close_days} days)",
+                dep.name, dep.bank
+            ));
         }
     }
-    Ok(())
+    messages
 }
 
 /**
@@ -160,31 +270,80 @@ Algorithm:
     - Calculate benefit for the rest of period, if deposit would be reopened
     - If benefit is greater then lose, display suggestion to reopen deposit it that bank
 */
-fn print_suggestions(deposits: &Vec<&Deposit>, banks: &Vec<&Bank>) {
-    let banks = utils::order_by(banks, |b1, b2| b2.percent.partial_cmp(&b1.percent).unwrap());
+fn print_suggestions(
+    deposits: &Vec<&Deposit>,
+    banks: &Vec<&Bank>,
+    transactions_by_deposit: &HashMap<String, Vec<Transaction>>,
+    exchange_rates: &ExchangeRates,
+    tax_profile: &TaxProfile,
+) -> Result<(), Box<dyn Error>> {
+    let lines = build_suggestions(
+        deposits,
+        banks,
+        transactions_by_deposit,
+        exchange_rates,
+        tax_profile,
+    )?;
+
+    if lines.is_empty() {
+        println!("{}", "No suggestions".green());
+    } else {
+        for (_, line) in lines {
+            println!("{line}");
+        }
+    }
+
+    Ok(())
+}
 
-    // calculate total amount across all deposits
+/// Builds the list of "reopen this deposit elsewhere" suggestions, paired with their net benefit
+/// in the base currency, so callers can either print all of them or pick the single best one.
+fn build_suggestions(
+    deposits: &Vec<&Deposit>,
+    banks: &Vec<&Bank>,
+    transactions_by_deposit: &HashMap<String, Vec<Transaction>>,
+    exchange_rates: &ExchangeRates,
+    tax_profile: &TaxProfile,
+) -> Result<Vec<(Decimal, String)>, Box<dyn Error>> {
+    let banks = utils::order_by(banks, |b1, b2| b2.percent.cmp(&b1.percent));
+
+    // calculate total amount across all deposits, normalized to the base currency
     let banks_by_name = utils::index_by(&banks, |bank| &bank.name);
-    let total_amount = calc_sum_amount(&deposits);
+    let total_amount = calc_sum_amount(&deposits, exchange_rates)?;
     let deposits_per_bank = utils::group_by(&deposits, |d| &d.bank);
     let total_amount_per_bank: HashMap<_, _> = deposits_per_bank
         .into_iter()
-        .map(|(key, bank_deps)| (key, calc_sum_amount(&bank_deps)))
-        .collect();
+        .map(|(key, bank_deps)| Ok((key, calc_sum_amount(&bank_deps, exchange_rates)?)))
+        .collect::<Result<_, Box<dyn Error>>>()?;
     let now = chrono::offset::Local::now().naive_local();
 
-    let mut lines: Vec<String> = vec![];
+    // interest the whole portfolio earns over each deposit's lifetime, per calendar year, so the
+    // tax-free allowance is only applied once across the whole portfolio rather than per deposit
+    let current_results: Vec<EmulationResult> = deposits
+        .iter()
+        .map(|&deposit| calc_depo_earn(deposit, deposit.date_close, transactions_by_deposit))
+        .collect::<Result<_, Box<dyn Error>>>()?;
+    let mut portfolio_earn_by_year_base: HashMap<i32, Decimal> = HashMap::new();
+    for (&deposit, result) in deposits.iter().zip(current_results.iter()) {
+        tax::merge_earn_by_year(
+            &mut portfolio_earn_by_year_base,
+            &convert_earn_by_year(&result.earn_by_year, &deposit.currency, exchange_rates)?,
+        );
+    }
 
-    for deposit in deposits {
+    let mut lines: Vec<(Decimal, String)> = vec![];
+
+    for (i, &deposit) in deposits.iter().enumerate() {
         let &self_bank = banks_by_name
             .get(&deposit.bank)
             .expect(format!("Unknown bank in deposit {:?}", deposit).as_str());
+        let deposit_amount_base = exchange_rates.to_base(&deposit.currency, deposit.amount)?;
         // find available banks, already sorted by percent DESC
         let mut best_bank = self_bank;
-        let mut transfer_comission: f32 = 0.0; // no comission for self bank
+        let mut transfer_comission = Decimal::ZERO; // no comission for self bank
         if check_diversification(
             self_bank,
-            -deposit.amount,
+            -deposit_amount_base,
             &total_amount_per_bank,
             total_amount,
             true,
@@ -197,7 +356,7 @@ fn print_suggestions(deposits: &Vec<&Deposit>, banks: &Vec<&Bank>) {
                 }
                 if !check_diversification(
                     bank,
-                    deposit.amount,
+                    deposit_amount_base,
                     &total_amount_per_bank,
                     total_amount,
                     false,
@@ -211,65 +370,132 @@ fn print_suggestions(deposits: &Vec<&Deposit>, banks: &Vec<&Bank>) {
             }
         }
 
-        let comission_amount = deposit.amount * transfer_comission;
-        let possible_earn = calc_earn(
-            deposit.amount,
+        let reopen_amount =
+            exchange_rates.convert(&deposit.currency, &best_bank.currency, deposit.amount)?;
+        let comission_amount = reopen_amount
+            .checked_mul(transfer_comission)
+            .expect("transfer comission overflow");
+        let possible_result = calc_earn(
+            reopen_amount,
             best_bank.percent,
             now,
             deposit.date_close,
             best_bank.pay_strategy,
-        ) - comission_amount;
-        let current_earn = calc_depo_earn(deposit, deposit.date_close);
-        let possible_benefit = possible_earn - current_earn;
+        )?;
+        let current_result = &current_results[i];
+
+        // exclude this deposit's own current earn from the allowance the rest of the
+        // portfolio has already used, so swapping it for the possible earn is a fair comparison
+        let current_earn_by_year_base =
+            convert_earn_by_year(&current_result.earn_by_year, &deposit.currency, exchange_rates)?;
+        let possible_earn_by_year_base = convert_earn_by_year(
+            &possible_result.earn_by_year,
+            &best_bank.currency,
+            exchange_rates,
+        )?;
+        let allowance_used_by_year =
+            tax::subtract_earn_by_year(&portfolio_earn_by_year_base, &current_earn_by_year_base);
+
+        let current_tax_base =
+            tax_profile.tax_on_by_year(&current_earn_by_year_base, &allowance_used_by_year);
+        let possible_tax_base =
+            tax_profile.tax_on_by_year(&possible_earn_by_year_base, &allowance_used_by_year);
+
+        let current_earn_after_tax_base = exchange_rates
+            .to_base(&deposit.currency, current_result.total_earn)?
+            .checked_sub(current_tax_base)
+            .expect("current earn after tax overflow");
+        let comission_amount_base =
+            exchange_rates.to_base(&best_bank.currency, comission_amount)?;
+        let possible_earn_after_tax_base = exchange_rates
+            .to_base(&best_bank.currency, possible_result.total_earn)?
+            .checked_sub(comission_amount_base)
+            .and_then(|v| v.checked_sub(possible_tax_base))
+            .expect("possible earn after tax overflow");
+        let possible_benefit = possible_earn_after_tax_base
+            .checked_sub(current_earn_after_tax_base)
+            .expect("possible benefit overflow");
+
         if possible_benefit >= MIN_BENEFIT {
-            lines.push(format!("Reopen deposit '{}' ({:.0}k) from {} to {} from {:.2}% to {:.2}% for extra earn {} (including transfer comission {:.2})", 
+            lines.push((possible_benefit, format!("Reopen deposit '{}' ({:.0}k {}) from {} to {} from {:.2}% to {:.2}% for extra net earn {} {} (including transfer comission {:.2} {})",
                 deposit.name,
-                deposit.amount/1000.0,
+                deposit.amount / Decimal::from(1000),
+                deposit.currency,
                 deposit.bank,
                 best_bank.name,
-                deposit.percent*100.0,
-                best_bank.percent*100.0,
+                deposit.percent * Decimal::from(100),
+                best_bank.percent * Decimal::from(100),
                 format!("{:.2}", possible_benefit).red().blink(),
+                exchange_rates.base_currency(),
                 comission_amount,
-            ));
+                best_bank.currency,
+            )));
         }
     }
 
-    if lines.len() > 0 {
-        for line in lines {
-            println!("{line}");
-        }
-    } else {
-        println!("{}", "No suggestions".green());
-    }
+    Ok(lines)
+}
+
+fn convert_earn_by_year(
+    earn_by_year: &HashMap<i32, Decimal>,
+    currency: &str,
+    exchange_rates: &ExchangeRates,
+) -> Result<HashMap<i32, Decimal>, Box<dyn Error>> {
+    earn_by_year
+        .iter()
+        .map(|(&year, &amount)| Ok((year, exchange_rates.to_base(currency, amount)?)))
+        .collect()
 }
 
 fn check_diversification(
     bank: &Bank,
-    amount_diff: f32,
-    total_amount_per_bank: &HashMap<&String, f32>,
-    total_amount: f32,
+    amount_diff: Decimal,
+    total_amount_per_bank: &HashMap<&String, Decimal>,
+    total_amount: Decimal,
     check_lower_bound: bool,
     check_upper_bound: bool,
 ) -> bool {
-    let possible_bank_amount = match total_amount_per_bank.get(&bank.name) {
-        Some(&total_amount) => total_amount,
-        None => 0.0,
-    } + amount_diff;
-    let possible_bank_capacity = possible_bank_amount / total_amount;
+    let possible_bank_amount = total_amount_per_bank
+        .get(&bank.name)
+        .copied()
+        .unwrap_or(Decimal::ZERO)
+        .checked_add(amount_diff)
+        .expect("diversification amount overflow");
+    let possible_bank_capacity = if total_amount.is_zero() {
+        Decimal::ZERO
+    } else {
+        possible_bank_amount
+            .checked_div(total_amount)
+            .expect("diversification capacity overflow")
+    };
     (!check_lower_bound || bank.min_capacity <= possible_bank_capacity)
         && (!check_upper_bound || possible_bank_capacity <= bank.max_capacity)
 }
 
-fn calc_sum_amount(deposits: &Vec<&Deposit>) -> f32 {
-    deposits.iter().fold(0.0 as f32, |acc, &e| acc + e.amount)
+fn calc_sum_amount(
+    deposits: &Vec<&Deposit>,
+    exchange_rates: &ExchangeRates,
+) -> Result<Decimal, Box<dyn Error>> {
+    let mut account = MultiCurrencyCashAccount::new();
+    for &deposit in deposits {
+        account.add(&deposit.currency, deposit.amount);
+    }
+    account.total_in_base(exchange_rates)
 }
 
-fn print_deposit_graph(deposits: &Vec<&Deposit>) {
+fn print_deposit_graph(
+    deposits: &Vec<&Deposit>,
+    banks: &Vec<&Bank>,
+    transactions_by_deposit: &HashMap<String, Vec<Transaction>>,
+    exchange_rates: &ExchangeRates,
+    tax_profile: &TaxProfile,
+) -> Result<(), Box<dyn Error>> {
     let mut deposits = deposits.clone();
     deposits.sort_by(|&d1, &d2| d2.date_close.cmp(&d1.date_close));
 
     let mut graph_lines: Vec<String> = vec![];
+    let mut percents: Vec<Decimal> = vec![];
+    let mut amounts_base: Vec<Decimal> = vec![];
 
     let graph_len = GRAPH_TOTAL_DAYS / GRAPH_CELL_DAYS;
     let today_shift = graph_len / 2.0;
@@ -283,13 +509,20 @@ fn print_deposit_graph(deposits: &Vec<&Deposit>) {
 
     let now = chrono::offset::Local::now().naive_local();
 
-    let mut total_amount: f32 = 0.0;
-    let mut weighted_percent: f32 = 0.0;
-    let mut earn_per_day: f32 = 0.0;
+    let mut amount_account = MultiCurrencyCashAccount::new();
+    let mut weighted_percent = Decimal::ZERO;
+    let mut earn_per_day = Decimal::ZERO;
+    let mut portfolio_earn_by_year_base: HashMap<i32, Decimal> = HashMap::new();
+    let mut amount_per_bank_base: HashMap<&str, Decimal> = HashMap::new();
 
     for dep in deposits {
-        let earned_now = calc_depo_earn(dep, now);
-        let earn_max = calc_depo_earn(dep, dep.date_close);
+        let earned_now = calc_depo_earn(dep, now, transactions_by_deposit)?.total_earn;
+        let earn_max_result = calc_depo_earn(dep, dep.date_close, transactions_by_deposit)?;
+        let earn_max = earn_max_result.total_earn;
+        tax::merge_earn_by_year(
+            &mut portfolio_earn_by_year_base,
+            &convert_earn_by_year(&earn_max_result.earn_by_year, &dep.currency, exchange_rates)?,
+        );
 
         let duration = dep.date_close - dep.date_open;
         let duration_days = duration.num_days();
@@ -307,10 +540,11 @@ fn print_deposit_graph(deposits: &Vec<&Deposit>) {
             "|".blue()
         ));
         graph_lines.push(format!(
-            "{:5} {:4.0}k for {:5.2}% {:12} close in days: {close_str:4}, duration days: {duration_days:4}, earned {earned_now:5.0} of {earn_max:5.0}",
+            "{:5} {:4.0}k {:3} for {:5.2}% {:12} close in days: {close_str:4}, duration days: {duration_days:4}, earned {earned_now:5.0} of {earn_max:5.0}",
             dep.bank,
-            dep.amount/1000.0,
-            dep.percent*100.0,
+            dep.amount / Decimal::from(1000),
+            dep.currency,
+            dep.percent * Decimal::from(100),
             ("'".to_owned() + dep.name.as_str() + "'").bold(),
         ));
 
@@ -331,107 +565,202 @@ fn print_deposit_graph(deposits: &Vec<&Deposit>) {
             "#".repeat(bar_len as usize).bold().purple().on_purple(),
         ));
 
-        total_amount += dep.amount;
-        weighted_percent += dep.amount * dep.percent;
-        earn_per_day += earn_max / duration_days as f32;
+        let amount_base = exchange_rates.to_base(&dep.currency, dep.amount)?;
+        amount_account.add(&dep.currency, dep.amount);
+        percents.push(dep.percent);
+        amounts_base.push(amount_base);
+        let bank_entry = amount_per_bank_base.entry(dep.bank.as_str()).or_insert(Decimal::ZERO);
+        *bank_entry = bank_entry
+            .checked_add(amount_base)
+            .expect("per-bank total overflow");
+        weighted_percent = weighted_percent
+            .checked_add(
+                amount_base
+                    .checked_mul(dep.percent)
+                    .expect("weighted percent overflow"),
+            )
+            .expect("weighted percent overflow");
+        earn_per_day = earn_per_day
+            .checked_add(
+                exchange_rates
+                    .to_base(&dep.currency, earn_max)?
+                    .checked_div(Decimal::from(duration_days))
+                    .expect("earn per day overflow"),
+            )
+            .expect("earn per day overflow");
     }
 
+    let total_amount = amount_account.total_in_base(exchange_rates)?;
+    let breakdown = amount_account
+        .breakdown()
+        .iter()
+        .map(|(currency, amount)| format!("{:.2}k {}", amount / Decimal::from(1000), currency))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let total_tax_base =
+        tax_profile.tax_on_by_year(&portfolio_earn_by_year_base, &HashMap::new());
+    let gross_total_earn_base: Decimal = portfolio_earn_by_year_base.values().copied().sum();
+    let net_ratio = if gross_total_earn_base > Decimal::ZERO {
+        (gross_total_earn_base - total_tax_base) / gross_total_earn_base
+    } else {
+        Decimal::ONE
+    };
+    let monthly_earn = earn_per_day * Decimal::new(305, 1) / Decimal::from(1000);
+
     graph_lines.push("".to_string());
     graph_lines.push(format!(
-        "{} {:.2}k  {} {:.2}%  {} {:.2}k",
-        "Sum:".bold(),
-        total_amount / 1000.0,
+        "{} {}  {} {:.2}k {}  {} {:.2}%  {} {:.2}k {}  {} {:.2}k {}",
+        "Sum by currency:".bold(),
+        breakdown,
+        "Total:".bold(),
+        total_amount / Decimal::from(1000),
+        exchange_rates.base_currency(),
         "Average percent:".bold(),
-        100.0
-            * if total_amount > 0.0 {
+        Decimal::from(100)
+            * if total_amount > Decimal::ZERO {
                 weighted_percent / total_amount
             } else {
-                0.0
+                Decimal::ZERO
             },
-        "Monthly earn:".bold(),
-        earn_per_day * 30.5 / 1000.0
+        "Monthly earn (gross):".bold(),
+        monthly_earn,
+        exchange_rates.base_currency(),
+        "Monthly earn (net of tax):".bold(),
+        monthly_earn * net_ratio,
+        exchange_rates.base_currency(),
     ));
 
+    graph_lines.push("".to_string());
+    if percents.is_empty() {
+        graph_lines.push(format!("{} no data", "Rate distribution:".bold()));
+        graph_lines.push(format!("{} no data", "Size distribution:".bold()));
+    } else {
+        graph_lines.push(format!(
+            "{} min {:.2}%  median {:.2}%  p75 {:.2}%  p90 {:.2}%  max {:.2}%",
+            "Rate distribution:".bold(),
+            percentile(&percents, 0.0) * Decimal::from(100),
+            percentile(&percents, 0.5) * Decimal::from(100),
+            percentile(&percents, 0.75) * Decimal::from(100),
+            percentile(&percents, 0.9) * Decimal::from(100),
+            percentile(&percents, 1.0) * Decimal::from(100),
+        ));
+        graph_lines.push(format!(
+            "{} min {:.2}k  median {:.2}k  p75 {:.2}k  p90 {:.2}k  max {:.2}k {}",
+            "Size distribution:".bold(),
+            percentile(&amounts_base, 0.0) / Decimal::from(1000),
+            percentile(&amounts_base, 0.5) / Decimal::from(1000),
+            percentile(&amounts_base, 0.75) / Decimal::from(1000),
+            percentile(&amounts_base, 0.9) / Decimal::from(1000),
+            percentile(&amounts_base, 1.0) / Decimal::from(1000),
+            exchange_rates.base_currency(),
+        ));
+    }
+
+    if !banks.is_empty() {
+        graph_lines.push("Bank concentration:".bold().to_string());
+        for &bank in banks {
+            let bank_amount = amount_per_bank_base
+                .get(bank.name.as_str())
+                .copied()
+                .unwrap_or(Decimal::ZERO);
+            let share = if total_amount > Decimal::ZERO {
+                bank_amount / total_amount
+            } else {
+                Decimal::ZERO
+            };
+            let in_bounds = bank.min_capacity <= share && share <= bank.max_capacity;
+            let share_str = format!("{:.2}%", share * Decimal::from(100));
+            let share_str = if in_bounds {
+                share_str.green()
+            } else {
+                share_str.red().blink()
+            };
+            graph_lines.push(format!(
+                "{:12} {share_str:8} (bounds {:.2}%..{:.2}%)",
+                bank.name,
+                bank.min_capacity * Decimal::from(100),
+                bank.max_capacity * Decimal::from(100),
+            ));
+        }
+    }
+
     for line in graph_lines {
         println!("{line}");
     }
+
+    Ok(())
+}
+
+/// Returns the value at percentile `pct` (0.0..=1.0) of `values`, which need not be sorted.
+/// `values` must not be empty; every call site already checks `is_empty()` first.
+fn percentile(values: &[Decimal], pct: f64) -> Decimal {
+    debug_assert!(!values.is_empty(), "percentile of an empty slice");
+    let mut sorted = values.to_vec();
+    sorted.sort();
+    let index = (((sorted.len() - 1) as f64) * pct).round() as usize;
+    sorted[index]
 }
 
-fn calc_depo_earn(deposit: &Deposit, date_end: NaiveDateTime) -> f32 {
-    calc_earn(
+fn calc_depo_earn(
+    deposit: &Deposit,
+    date_end: NaiveDateTime,
+    transactions_by_deposit: &HashMap<String, Vec<Transaction>>,
+) -> Result<EmulationResult, Box<dyn Error>> {
+    let transactions = transactions_by_deposit
+        .get(&deposit.name)
+        .map(Vec::as_slice)
+        .unwrap_or(&[]);
+    DepositEmulator::emulate(
         deposit.amount,
         deposit.percent,
         deposit.date_open,
         date_end,
         deposit.pay_strategy,
+        transactions,
     )
 }
 
 fn calc_earn(
-    initial_amount: f32,
-    percent: f32,
+    initial_amount: Decimal,
+    percent: Decimal,
     date_start: NaiveDateTime,
     date_end: NaiveDateTime,
     pay_strategy: PayStrategy,
-) -> f32 {
-    let percent_per_day = percent / 365.25; // does leap year matter?
-    let mut amount = initial_amount;
-    let mut date = date_start;
-    let mut stop = false;
-    let mut total_earn: f32 = 0.0;
-    while !stop {
-        let mut next_date = date.checked_add_months(Months::new(1)).unwrap();
-        if next_date > date_end {
-            next_date = date_end;
-            stop = true;
-        }
-        let payable_days = next_date - date;
-        let earn = amount * payable_days.num_days() as f32 * percent_per_day;
-        if pay_strategy == PayStrategy::Capitalization {
-            amount += earn;
-        }
-        total_earn += earn;
-        if stop {
-            break;
-        }
-        date = next_date;
-    }
-    return total_earn;
+) -> Result<EmulationResult, Box<dyn Error>> {
+    DepositEmulator::emulate(
+        initial_amount,
+        percent,
+        date_start,
+        date_end,
+        pay_strategy,
+        &[],
+    )
 }
 
-fn read_sheet<T, R, RS>(doc: &mut R, sheet_name: &str) -> Result<Vec<T>, Box<dyn Error>>
-where
-    RS: Seek + Read,
-    R: Reader<RS>,
-    T: DeserializeOwned,
-{
-    let sheet = doc
-        .worksheet_range(sheet_name)
-        .ok_or(format!("Can not open sheet {sheet_name}"))?
-        .map_err(|err| format!("Failed to parse sheet: {:?}", err))?;
-
-    let mut ret: Vec<T> = Vec::new();
-    let mut iter = RangeDeserializerBuilder::new().from_range::<_, T>(&sheet)?;
-    while let Some(Ok(row)) = iter.next() {
-        ret.push(row);
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(ret)
-}
+    #[test]
+    fn test_percentile_single_element() {
+        let values = [Decimal::new(42, 0)];
 
-fn parse_date_time<'de, D>(deserializer: D) -> Result<NaiveDateTime, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let data_type = calamine::DataType::deserialize(deserializer)?;
-    match &data_type {
-        DataType::String(val) => {
-            if !val.contains("T") {
-                Ok(NaiveDateTime::from_str(&(val.clone() + "T00:00:00")).unwrap())
-            } else {
-                Ok(NaiveDateTime::from_str(val).unwrap())
-            }
-        }
-        _ => panic!("Invalid DataType for DateTime: {:?}", data_type),
+        assert_eq!(percentile(&values, 0.0), Decimal::new(42, 0));
+        assert_eq!(percentile(&values, 1.0), Decimal::new(42, 0));
+    }
+
+    #[test]
+    fn test_percentile_unsorted_input() {
+        let values = [
+            Decimal::new(30, 0),
+            Decimal::new(10, 0),
+            Decimal::new(20, 0),
+        ];
+
+        assert_eq!(percentile(&values, 0.0), Decimal::new(10, 0));
+        assert_eq!(percentile(&values, 0.5), Decimal::new(20, 0));
+        assert_eq!(percentile(&values, 1.0), Decimal::new(30, 0));
     }
 }
+
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+
+/// Interest-income tax rules for a single country: a flat rate plus an annual tax-free
+/// allowance that applies once across the whole portfolio, not per deposit.
+#[derive(Debug, Clone, Copy)]
+pub struct TaxProfile {
+    pub tax_rate: Decimal,
+    pub annual_allowance: Decimal,
+}
+
+impl TaxProfile {
+    pub fn none() -> Self {
+        Self {
+            tax_rate: Decimal::ZERO,
+            annual_allowance: Decimal::ZERO,
+        }
+    }
+
+    /// Resolves a tax profile from an ISO country code. Unknown or absent codes mean no tax,
+    /// so the tool keeps working out of the box for users who don't pass `--country`.
+    pub fn for_country(country: Option<&str>) -> Self {
+        match country.map(str::to_uppercase).as_deref() {
+            Some("RU") => Self {
+                tax_rate: Decimal::new(13, 2),
+                annual_allowance: Decimal::from(150_000),
+            },
+            Some("US") => Self {
+                tax_rate: Decimal::new(24, 2),
+                annual_allowance: Decimal::ZERO,
+            },
+            _ => Self::none(),
+        }
+    }
+
+    /// Tax owed on `interest` earned in a single calendar year, given that `allowance_used`
+    /// of the annual allowance has already been claimed elsewhere in the portfolio.
+    pub fn tax_on(&self, interest: Decimal, allowance_used: Decimal) -> Decimal {
+        let remaining_allowance = (self.annual_allowance - allowance_used).max(Decimal::ZERO);
+        let taxable = (interest - remaining_allowance).max(Decimal::ZERO);
+        taxable
+            .checked_mul(self.tax_rate)
+            .expect("tax calculation overflow")
+    }
+
+    /// Sums tax owed across every calendar year present in `earn_by_year`, consuming
+    /// `allowance_used_by_year` as the allowance already spent in each of those years.
+    pub fn tax_on_by_year(
+        &self,
+        earn_by_year: &HashMap<i32, Decimal>,
+        allowance_used_by_year: &HashMap<i32, Decimal>,
+    ) -> Decimal {
+        earn_by_year
+            .iter()
+            .try_fold(Decimal::ZERO, |acc, (year, &interest)| {
+                let used = allowance_used_by_year
+                    .get(year)
+                    .copied()
+                    .unwrap_or(Decimal::ZERO);
+                acc.checked_add(self.tax_on(interest, used))
+            })
+            .expect("tax calculation overflow")
+    }
+}
+
+/// Merges `b` into `a`, summing amounts for years present in both.
+pub fn merge_earn_by_year(a: &mut HashMap<i32, Decimal>, b: &HashMap<i32, Decimal>) {
+    for (&year, &amount) in b {
+        let entry = a.entry(year).or_insert(Decimal::ZERO);
+        *entry = entry.checked_add(amount).expect("earn by year overflow");
+    }
+}
+
+/// Returns `a` with `b`'s per-year amounts subtracted, used to exclude a single deposit's own
+/// contribution from the rest of the portfolio's allowance usage.
+pub fn subtract_earn_by_year(
+    a: &HashMap<i32, Decimal>,
+    b: &HashMap<i32, Decimal>,
+) -> HashMap<i32, Decimal> {
+    let mut result = a.clone();
+    for (&year, &amount) in b {
+        let entry = result.entry(year).or_insert(Decimal::ZERO);
+        *entry = entry.checked_sub(amount).expect("earn by year overflow");
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ru_profile() -> TaxProfile {
+        TaxProfile::for_country(Some("RU"))
+    }
+
+    #[test]
+    fn test_tax_on_allowance_fully_consumed_elsewhere() {
+        let profile = ru_profile();
+
+        // the rest of the portfolio already used up the whole annual allowance, so this
+        // deposit's interest is fully taxable
+        let tax = profile.tax_on(Decimal::from(1000), profile.annual_allowance);
+
+        assert_eq!(tax, Decimal::from(1000) * profile.tax_rate);
+    }
+
+    #[test]
+    fn test_tax_on_by_year_splits_across_a_year_boundary() {
+        let profile = ru_profile();
+
+        // a deposit spanning new year's eve, split the way deposit_emulator's
+        // split_earn_by_year would split it
+        let mut earn_by_year = HashMap::new();
+        earn_by_year.insert(2022, Decimal::from(100_000));
+        earn_by_year.insert(2023, Decimal::from(200_000));
+
+        let mut allowance_used_by_year = HashMap::new();
+        allowance_used_by_year.insert(2022, Decimal::ZERO);
+        allowance_used_by_year.insert(2023, Decimal::from(100_000));
+
+        let tax = profile.tax_on_by_year(&earn_by_year, &allowance_used_by_year);
+
+        // 2022: 100_000 earn, 150_000 allowance untouched -> no tax
+        // 2023: 200_000 earn, 50_000 allowance left -> 150_000 taxable
+        let expected = profile.tax_on(Decimal::from(100_000), Decimal::ZERO)
+            + profile.tax_on(Decimal::from(200_000), Decimal::from(100_000));
+        assert_eq!(tax, expected);
+        assert_eq!(tax, Decimal::from(150_000) * profile.tax_rate);
+    }
+
+    #[test]
+    fn test_subtract_earn_by_year_can_land_on_exactly_zero() {
+        let mut a = HashMap::new();
+        a.insert(2023, Decimal::from(500));
+
+        let mut b = HashMap::new();
+        b.insert(2023, Decimal::from(500));
+
+        let result = subtract_earn_by_year(&a, &b);
+
+        assert_eq!(result[&2023], Decimal::ZERO);
+    }
+}